@@ -6,13 +6,14 @@
 
 use std::collections::{HashMap, HashSet};
 use std::ffi::CStr;
-use std::io::Cursor;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
 
 use byteorder::{LittleEndian, ReadBytesExt};
-use cpython::exc::{ImportError, RuntimeError, ValueError};
+use cpython::exc::{FileNotFoundError, ImportError, RuntimeError, ValueError};
 use cpython::{
     py_class, py_class_impl, py_coerce_item, py_fn, NoArgs, ObjectProtocol, PyDict, PyErr, PyList,
-    PyModule, PyObject, PyResult, PyString, Python, PythonObject,
+    PyModule, PyObject, PyResult, PyString, Python, PythonObject, ToPyObject,
 };
 use python3_sys as pyffi;
 use python3_sys::{PyBUF_READ, PyMemoryView_FromMemory};
@@ -45,6 +46,82 @@ impl PythonModulesData {
             None => None,
         }
     }
+
+    /// Whether a given key is present.
+    fn contains(&self, name: &str) -> bool {
+        self.data.contains_key(name)
+    }
+
+    /// Obtain the keys present in this instance.
+    fn names(&self) -> Vec<&'static str> {
+        self.data.keys().cloned().collect()
+    }
+
+    /// Obtain the raw bytes for a specific key.
+    fn get_bytes(&self, name: &str) -> Option<&'static [u8]> {
+        self.data.get(name).copied()
+    }
+}
+
+/// Represents resource (non-module package data) blobs in memory.
+///
+/// Data is keyed by `(package, resource name)` so multiple packages can
+/// ship resources with the same name without colliding.
+struct PythonPackageResources {
+    data: HashMap<(&'static str, &'static str), &'static [u8]>,
+}
+
+impl PythonPackageResources {
+    /// Obtain a `PythonModulesData` restricted to the resources of a single package.
+    fn for_package(&self, package: &str) -> PythonModulesData {
+        let data = self
+            .data
+            .iter()
+            .filter_map(|((p, name), value)| {
+                if *p == package {
+                    Some((*name, *value))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        PythonModulesData { data }
+    }
+}
+
+/// Represents per-distribution metadata file blobs in memory.
+///
+/// Data is keyed by `(distribution name, metadata file name)`, mirroring
+/// `PythonPackageResources`. A distribution's metadata typically consists
+/// of files like `METADATA`, `RECORD`, `entry_points.txt`, and
+/// `top_level.txt`.
+struct PythonDistributionsData {
+    data: HashMap<(&'static str, &'static str), &'static [u8]>,
+}
+
+impl PythonDistributionsData {
+    /// Obtain a `PythonModulesData` restricted to the metadata files of a single distribution.
+    fn for_distribution(&self, name: &str) -> PythonModulesData {
+        let data = self
+            .data
+            .iter()
+            .filter_map(|((dist, filename), value)| {
+                if *dist == name {
+                    Some((*filename, *value))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        PythonModulesData { data }
+    }
+
+    /// Obtain the set of distribution names present.
+    fn distribution_names(&self) -> HashSet<&'static str> {
+        self.data.keys().map(|(dist, _)| *dist).collect()
+    }
 }
 
 /// Parse modules blob data into a map of module name to module data.
@@ -89,6 +166,343 @@ fn parse_modules_blob(data: &'static [u8]) -> Result<HashMap<&str, &[u8]>, &'sta
     Ok(res)
 }
 
+/// Magic bytes identifying a versioned, compressed modules blob.
+///
+/// A blob lacking this prefix is the legacy v1 format handled directly by
+/// `parse_modules_blob`: a bare `u32` count followed by `(name_len,
+/// data_len)` pairs, with entries always uncompressed and no baked-in
+/// package information.
+const MODULES_BLOB_MAGIC: &[u8; 4] = b"PYOX";
+
+/// Current version of the versioned modules blob format.
+const MODULES_BLOB_VERSION: u8 = 2;
+
+/// Entry flag indicating the value bytes are zstd-compressed.
+const MODULE_FLAG_COMPRESSED: u8 = 0b0000_0001;
+
+/// Entry flag indicating the module is a package (has `__path__`).
+const MODULE_FLAG_PACKAGE: u8 = 0b0000_0010;
+
+/// A single module's metadata within a versioned modules blob.
+#[derive(Clone, Copy)]
+struct ModuleBlobEntry {
+    data: &'static [u8],
+    compressed: bool,
+}
+
+/// Represents Python module (source/bytecode) blobs in memory.
+///
+/// Unlike `PythonModulesData`, entries may individually be zstd-compressed,
+/// so callers must go through `get_memory_view()` -- which consults (and
+/// populates) the decompression cache in `ModuleState` -- rather than
+/// reaching into `entries` directly.
+struct PythonModulesBlob {
+    entries: HashMap<&'static str, ModuleBlobEntry>,
+}
+
+impl PythonModulesBlob {
+    /// Obtain a PyMemoryView for a module.
+    ///
+    /// Uncompressed entries are served zero-copy, same as `PythonModulesData`.
+    /// Compressed entries are inflated into `cache` on first access and served
+    /// from there on subsequent calls.
+    fn get_memory_view(
+        &self,
+        py: Python,
+        cache: &mut HashMap<&'static str, Vec<u8>>,
+        name: &str,
+    ) -> PyResult<Option<PyObject>> {
+        let (name, entry) = match self.entries.get_key_value(name) {
+            Some((name, entry)) => (*name, *entry),
+            None => return Ok(None),
+        };
+
+        if !entry.compressed {
+            return Ok(get_memory_view(py, entry.data));
+        }
+
+        if !cache.contains_key(name) {
+            let inflated = zstd::decode_all(entry.data).map_err(|e| {
+                PyErr::new::<ValueError, _>(
+                    py,
+                    format!("error decompressing module {}: {}", name, e),
+                )
+            })?;
+
+            cache.insert(name, inflated);
+        }
+
+        let buffer = cache.get(name).unwrap();
+
+        // SAFETY: `cache` lives inside the `ModuleState` owned by the importer
+        // module object, and `module_init()` leaks a strong reference to that
+        // object so it's never deallocated before interpreter shutdown -- making
+        // `cache`, and this borrow out of it, effectively `'static`.
+        let buffer: &'static [u8] = unsafe { std::mem::transmute(buffer.as_slice()) };
+
+        Ok(get_memory_view(py, buffer))
+    }
+}
+
+/// Parse a (possibly versioned and compressed) Python modules blob.
+fn parse_versioned_modules_blob(
+    data: &'static [u8],
+) -> Result<
+    (
+        HashMap<&'static str, ModuleBlobEntry>,
+        HashSet<&'static str>,
+    ),
+    &'static str,
+> {
+    if data.len() >= MODULES_BLOB_MAGIC.len()
+        && data[0..MODULES_BLOB_MAGIC.len()] == *MODULES_BLOB_MAGIC
+    {
+        parse_modules_blob_v2(data)
+    } else {
+        parse_modules_blob_v1(data)
+    }
+}
+
+/// Parse the legacy, always-uncompressed, unversioned modules blob format.
+fn parse_modules_blob_v1(
+    data: &'static [u8],
+) -> Result<
+    (
+        HashMap<&'static str, ModuleBlobEntry>,
+        HashSet<&'static str>,
+    ),
+    &'static str,
+> {
+    let raw = parse_modules_blob(data)?;
+
+    let mut entries = HashMap::with_capacity(raw.len());
+    let mut packages = HashSet::with_capacity(raw.len());
+
+    for (name, data) in raw {
+        populate_packages(&mut packages, name);
+        entries.insert(
+            name,
+            ModuleBlobEntry {
+                data,
+                compressed: false,
+            },
+        );
+    }
+
+    Ok((entries, packages))
+}
+
+/// Parse the versioned, per-entry-compressible modules blob format.
+///
+/// Layout: `PYOX` magic, `u8` version, `u32` count, then per entry a
+/// `(name_len: u32, data_len: u32, flags: u8)` triple, followed by all names
+/// contiguously and then all (possibly compressed) values contiguously --
+/// mirroring `parse_modules_blob`'s layout aside from the header and flags.
+fn parse_modules_blob_v2(
+    data: &'static [u8],
+) -> Result<
+    (
+        HashMap<&'static str, ModuleBlobEntry>,
+        HashSet<&'static str>,
+    ),
+    &'static str,
+> {
+    let header_len = MODULES_BLOB_MAGIC.len() + 1;
+
+    if data.len() < header_len + 4 {
+        return Err("modules data too small");
+    }
+
+    if data[MODULES_BLOB_MAGIC.len()] != MODULES_BLOB_VERSION {
+        return Err("unsupported modules blob version");
+    }
+
+    let mut reader = Cursor::new(&data[header_len..]);
+
+    let count = reader.read_u32::<LittleEndian>().unwrap();
+    let mut index = Vec::with_capacity(count as usize);
+    let mut total_names_length = 0;
+
+    let mut i = 0;
+    while i < count {
+        let name_length = reader.read_u32::<LittleEndian>().unwrap() as usize;
+        let data_length = reader.read_u32::<LittleEndian>().unwrap() as usize;
+        let flags = reader.read_u8().unwrap();
+
+        index.push((name_length, data_length, flags));
+        total_names_length += name_length;
+        i += 1;
+    }
+
+    let mut entries = HashMap::with_capacity(count as usize);
+    let mut packages = HashSet::with_capacity(count as usize);
+    let values_start_offset = header_len + reader.position() as usize + total_names_length;
+    let mut values_current_offset: usize = 0;
+
+    for (name_length, value_length, flags) in index {
+        let offset = header_len + reader.position() as usize;
+
+        let name = unsafe { std::str::from_utf8_unchecked(&data[offset..offset + name_length]) };
+
+        let value_offset = values_start_offset + values_current_offset;
+        let value = &data[value_offset..value_offset + value_length];
+        reader.set_position((offset - header_len) as u64 + name_length as u64);
+        values_current_offset += value_length;
+
+        if flags & MODULE_FLAG_PACKAGE != 0 {
+            packages.insert(name);
+        }
+
+        entries.insert(
+            name,
+            ModuleBlobEntry {
+                data: value,
+                compressed: flags & MODULE_FLAG_COMPRESSED != 0,
+            },
+        );
+    }
+
+    Ok((entries, packages))
+}
+
+/// Parse resources blob data into a map of (package, resource) to resource data.
+///
+/// The layout mirrors `parse_modules_blob` except each entry carries two
+/// names (the owning package and the resource name) instead of one.
+fn parse_resources_blob(data: &'static [u8]) -> Result<HashMap<(&str, &str), &[u8]>, &'static str> {
+    if data.len() < 4 {
+        return Err("resources data too small");
+    }
+
+    let mut reader = Cursor::new(data);
+
+    let count = reader.read_u32::<LittleEndian>().unwrap();
+    let mut index = Vec::with_capacity(count as usize);
+    let mut total_names_length = 0;
+
+    let mut i = 0;
+    while i < count {
+        let package_length = reader.read_u32::<LittleEndian>().unwrap() as usize;
+        let name_length = reader.read_u32::<LittleEndian>().unwrap() as usize;
+        let data_length = reader.read_u32::<LittleEndian>().unwrap() as usize;
+
+        index.push((package_length, name_length, data_length));
+        total_names_length += package_length + name_length;
+        i += 1;
+    }
+
+    let mut res = HashMap::with_capacity(count as usize);
+    let values_start_offset = reader.position() as usize + total_names_length;
+    let mut values_current_offset: usize = 0;
+
+    for (package_length, name_length, value_length) in index {
+        let offset = reader.position() as usize;
+
+        let package =
+            unsafe { std::str::from_utf8_unchecked(&data[offset..offset + package_length]) };
+        let name_offset = offset + package_length;
+        let name =
+            unsafe { std::str::from_utf8_unchecked(&data[name_offset..name_offset + name_length]) };
+
+        let value_offset = values_start_offset + values_current_offset;
+        let value = &data[value_offset..value_offset + value_length];
+        reader.set_position(name_offset as u64 + name_length as u64);
+        values_current_offset += value_length;
+
+        res.insert((package, name), value);
+    }
+
+    Ok(res)
+}
+
+/// Python source defining a subclass of importlib.metadata.Distribution.
+///
+/// `PyOxidizerDistribution` instances implement the abstract `read_text()`
+/// and `locate_file()` methods required by `importlib.metadata.Distribution`
+/// but, being a native type, cannot subclass that pure Python class
+/// directly. This source is executed once during `module_setup` to produce
+/// a thin Python subclass that delegates both abstract methods to a
+/// `PyOxidizerDistribution` backend instance, picking up `Distribution`'s
+/// concrete `metadata`/`version`/`entry_points` mixin methods for free.
+const DISTRIBUTION_CLASS_SOURCE: &str = r#"
+def _pyoxidizer_make_distribution_class(base):
+    class PyOxidizerDistribution(base):
+        def __init__(self, backend):
+            self._pyoxidizer_backend = backend
+
+        def read_text(self, filename):
+            return self._pyoxidizer_backend.read_text(filename)
+
+        def locate_file(self, path):
+            return self._pyoxidizer_backend.locate_file(path)
+
+    return PyOxidizerDistribution
+"#;
+
+#[allow(unused_doc_comments)]
+/// Python type backing a dynamically created importlib.metadata.Distribution subclass.
+///
+/// Instances are bound to a single in-memory distribution and serve that
+/// distribution's metadata files without the files needing to exist on disk.
+py_class!(class PyOxidizerDistribution |py| {
+    data metadata: PythonModulesData;
+
+    def read_text(&self, filename: &PyString) -> PyResult<PyObject> {
+        let filename = filename.to_string(py)?;
+
+        match self.metadata(py).get_bytes(&filename) {
+            Some(value) => match std::str::from_utf8(value) {
+                Ok(text) => Ok(text.to_py_object(py).into_object()),
+                Err(_) => Err(PyErr::new::<ValueError, _>(py, "distribution metadata is not valid UTF-8")),
+            },
+            None => Ok(py.None()),
+        }
+    }
+
+    def locate_file(&self, _path: &PyObject) -> PyResult<PyObject> {
+        Err(PyErr::new::<FileNotFoundError, _>(py, "distribution data does not have a file system path"))
+    }
+});
+
+#[allow(unused_doc_comments)]
+/// Python type implementing importlib.abc.ResourceReader.
+///
+/// Instances are bound to a single in-memory package and serve that
+/// package's resource (non-module) data files without the data needing to
+/// exist on disk.
+py_class!(class PyOxidizerResourceReader |py| {
+    data package: String;
+    data resources: PythonModulesData;
+
+    def open_resource(&self, resource: &PyString) -> PyResult<PyObject> {
+        let name = resource.to_string(py)?;
+
+        match self.resources(py).get_memory_view(py, &name) {
+            Some(value) => {
+                let io_module = py.import("io")?;
+                io_module.call(py, "BytesIO", (value,), None)
+            }
+            None => Err(PyErr::new::<FileNotFoundError, _>(py, (format!("resource not found: {}", name),))),
+        }
+    }
+
+    def resource_path(&self, _resource: &PyObject) -> PyResult<PyObject> {
+        Err(PyErr::new::<FileNotFoundError, _>(py, "resource does not have a file system path"))
+    }
+
+    def is_resource(&self, name: &PyString) -> PyResult<bool> {
+        let name = name.to_string(py)?;
+
+        Ok(self.resources(py).contains(&name))
+    }
+
+    def contents(&self) -> PyResult<PyObject> {
+        let names: Vec<PyObject> = self.resources(py).names().into_iter().map(|name| name.to_py_object(py).into_object()).collect();
+
+        Ok(PyList::new(py, &names).into_object())
+    }
+});
+
 #[allow(unused_doc_comments)]
 /// Python type to import modules.
 ///
@@ -97,6 +511,7 @@ fn parse_modules_blob(data: &'static [u8]) -> Result<HashMap<&str, &[u8]>, &'sta
 /// allowing it to be the only registered sys.meta_path importer.
 py_class!(class PyOxidizerFinder |py| {
     data imp_module: PyModule;
+    data importer_module: PyModule;
     data marshal_loads: PyObject;
     data builtin_importer: PyObject;
     data frozen_importer: PyObject;
@@ -104,10 +519,20 @@ py_class!(class PyOxidizerFinder |py| {
     data module_spec_type: PyObject;
     data decode_source: PyObject;
     data exec_fn: PyObject;
-    data py_modules: PythonModulesData;
-    data pyc_modules: PythonModulesData;
+    data sys_audit: PyObject;
+    data sys_path: PyObject;
+    data sys_meta_path: PyObject;
+    data sys_path_hooks: PyObject;
+    data py_modules: PythonModulesBlob;
+    data pyc_modules: PythonModulesBlob;
+    data extension_modules: PythonModulesData;
+    data extension_file_loader: PyObject;
+    data resources: PythonPackageResources;
+    data distributions: PythonDistributionsData;
+    data distribution_class: PyObject;
     data packages: HashSet<&'static str>;
     data known_modules: KnownModules;
+    data set_module_origin: bool;
 
     // Start of importlib.abc.MetaPathFinder interface.
 
@@ -125,13 +550,36 @@ py_class!(class PyOxidizerFinder |py| {
                 KnownModuleFlavor::InMemory => {
                     let is_package = self.packages(py).contains(&*key);
 
-                    // TODO consider setting origin and has_location so __file__ will be
-                    // populated.
-
                     let kwargs = PyDict::new(py);
                     kwargs.set_item(py, "is_package", is_package)?;
 
-                    self.module_spec_type(py).call(py, (fullname, self), Some(&kwargs))
+                    if *self.set_module_origin(py) {
+                        kwargs.set_item(py, "origin", synthetic_module_path(&key, is_package))?;
+                    }
+
+                    let spec = self.module_spec_type(py).call(py, (fullname, self), Some(&kwargs))?;
+
+                    if *self.set_module_origin(py) {
+                        spec.setattr(py, "has_location", true)?;
+                    }
+
+                    Ok(spec)
+                }
+                KnownModuleFlavor::InMemoryExtension => {
+                    // `_imp.create_dynamic()`/`_imp.exec_dynamic()` dlopen() the path
+                    // in `spec.origin`, not the loader's own `path` attribute, so the
+                    // extension must be materialized and its real path set as the
+                    // spec's origin here rather than only in create_module/exec_module.
+                    let origin = self.materialize_extension(py, &key)?;
+
+                    let kwargs = PyDict::new(py);
+                    kwargs.set_item(py, "is_package", false)?;
+                    kwargs.set_item(py, "origin", origin)?;
+
+                    let spec = self.module_spec_type(py).call(py, (fullname, self), Some(&kwargs))?;
+                    spec.setattr(py, "has_location", true)?;
+
+                    Ok(spec)
                 }
             }
         } else {
@@ -153,7 +601,16 @@ py_class!(class PyOxidizerFinder |py| {
 
     // Start of importlib.abc.Loader interface.
 
-    def create_module(&self, _spec: &PyObject) -> PyResult<PyObject> {
+    def create_module(&self, spec: &PyObject) -> PyResult<PyObject> {
+        let fullname = spec.getattr(py, "name")?;
+        let key = fullname.extract::<String>(py)?;
+
+        if let Some(KnownModuleFlavor::InMemoryExtension) = self.known_modules(py).get(&*key) {
+            let loader = self.extension_loader_for(py, &fullname, &key)?;
+
+            return loader.call_method(py, "create_module", (spec,), None);
+        }
+
         Ok(py.None())
     }
 
@@ -170,7 +627,24 @@ py_class!(class PyOxidizerFinder |py| {
                     self.frozen_importer(py).call_method(py, "exec_module", (module,), None)
                 },
                 KnownModuleFlavor::InMemory => {
-                    match self.pyc_modules(py).get_memory_view(py, &*key) {
+                    // CPython's normal import machinery emits a "import" audit event for
+                    // every module load. We bypass that machinery, so replicate it here.
+                    let is_package = self.packages(py).contains(&*key);
+                    let filename = synthetic_module_path(&key, is_package);
+                    self.sys_audit(py).call(
+                        py,
+                        (
+                            "import",
+                            &name,
+                            filename,
+                            self.sys_path(py),
+                            self.sys_meta_path(py),
+                            self.sys_path_hooks(py),
+                        ),
+                        None,
+                    )?;
+
+                    match self.get_pyc_module_memory_view(py, &key)? {
                         Some(value) => {
                             let code = self.marshal_loads(py).call(py, (value,), None)?;
                             let exec_fn = self.exec_fn(py);
@@ -183,6 +657,11 @@ py_class!(class PyOxidizerFinder |py| {
                         }
                     }
                 },
+                KnownModuleFlavor::InMemoryExtension => {
+                    let loader = self.extension_loader_for(py, &name, &key)?;
+
+                    loader.call_method(py, "exec_module", (module,), None)
+                },
             }
         } else {
             // Raising here might make more sense, as exec_module() shouldn't
@@ -206,7 +685,7 @@ py_class!(class PyOxidizerFinder |py| {
                     imp_module.call(py, "get_frozen_object", (fullname,), None)
                 },
                 KnownModuleFlavor::InMemory => {
-                    match self.pyc_modules(py).get_memory_view(py, &*key) {
+                    match self.get_pyc_module_memory_view(py, &key)? {
                         Some(value) => {
                             self.marshal_loads(py).call(py, (value,), None)
                         }
@@ -215,7 +694,7 @@ py_class!(class PyOxidizerFinder |py| {
                         }
                     }
                 },
-                KnownModuleFlavor::Builtin => {
+                KnownModuleFlavor::Builtin | KnownModuleFlavor::InMemoryExtension => {
                     Ok(py.None())
                 }
             }
@@ -229,7 +708,7 @@ py_class!(class PyOxidizerFinder |py| {
 
         if let Some(flavor) = self.known_modules(py).get(&*key) {
             if let KnownModuleFlavor::InMemory = flavor {
-                match self.py_modules(py).get_memory_view(py, &*key) {
+                match self.get_py_module_memory_view(py, &key)? {
                     Some(value) => {
                         self.decode_source(py).call(py, (value,), None)
                     },
@@ -246,8 +725,157 @@ py_class!(class PyOxidizerFinder |py| {
     }
 
     // End of importlib.abc.InspectLoader interface.
+
+    // Start of importlib.abc.ExecutionLoader interface.
+
+    def get_filename(&self, fullname: &PyString) -> PyResult<PyObject> {
+        let key = fullname.to_string(py)?;
+
+        match self.known_modules(py).get(&*key) {
+            // Embedders who set `set_module_origin=false` want no `__file__` at all,
+            // since a non-existent one can change `pkgutil`/`inspect` behavior --
+            // `find_spec()` honors that for `spec.origin`, so do the same here for
+            // code that consults the loader directly instead (e.g. `linecache`).
+            Some(KnownModuleFlavor::InMemory) if *self.set_module_origin(py) => {
+                let is_package = self.packages(py).contains(&*key);
+
+                Ok(synthetic_module_path(&key, is_package).to_py_object(py).into_object())
+            }
+            // `find_spec()` sets `self` as `loader` on extension module specs too, so
+            // callers that invoke the `ExecutionLoader` contract directly on the
+            // loader (e.g. `pkgutil`, `linecache`) need a real answer here rather than
+            // the "source not available" fallback below.
+            Some(KnownModuleFlavor::InMemoryExtension) => {
+                Ok(self.materialize_extension(py, &key)?.to_py_object(py).into_object())
+            }
+            _ => Err(PyErr::new::<ImportError, _>(py, ("source not available", fullname))),
+        }
+    }
+
+    // End of importlib.abc.ExecutionLoader interface.
+
+    // Start of importlib.resources support.
+
+    def get_resource_reader(&self, fullname: &PyString) -> PyResult<PyObject> {
+        let key = fullname.to_string(py)?;
+
+        if !self.packages(py).contains(&*key) {
+            return Ok(py.None());
+        }
+
+        match self.known_modules(py).get(&*key) {
+            Some(KnownModuleFlavor::InMemory) => {
+                let resources = self.resources(py).for_package(&key);
+
+                Ok(PyOxidizerResourceReader::create_instance(py, key.to_string(), resources)?.into_object())
+            }
+            _ => Ok(py.None()),
+        }
+    }
+
+    // End of importlib.resources support.
+
+    // Start of importlib.metadata.DistributionFinder interface.
+
+    def find_distributions(&self, context: &PyObject) -> PyResult<PyObject> {
+        let name = context.getattr(py, "name")?;
+
+        let names: Vec<&'static str> = if name.is_none(py) {
+            self.distributions(py).distribution_names().into_iter().collect()
+        } else {
+            let name = name.extract::<String>(py)?;
+
+            self.distributions(py)
+                .distribution_names()
+                .into_iter()
+                .filter(|candidate| **candidate == name)
+                .collect()
+        };
+
+        let mut dists = Vec::with_capacity(names.len());
+
+        for name in names {
+            let metadata = self.distributions(py).for_distribution(name);
+            let backend = PyOxidizerDistribution::create_instance(py, metadata)?;
+            let dist = self.distribution_class(py).call(py, (backend,), None)?;
+
+            dists.push(dist);
+        }
+
+        Ok(PyList::new(py, &dists).into_object())
+    }
+
+    // End of importlib.metadata.DistributionFinder interface.
 });
 
+impl PyOxidizerFinder {
+    /// Materialize an in-memory extension module to a temp file and return its path.
+    ///
+    /// This is idempotent: the destination path is deterministic given `key` within
+    /// this importer instance's own private temp directory, so calling it again
+    /// (e.g. once from `find_spec()` to populate `origin` and again from
+    /// `create_module()`/`exec_module()`) is a cheap no-op the second time.
+    fn materialize_extension(&self, py: Python, key: &str) -> PyResult<String> {
+        let data = match self.extension_modules(py).get_bytes(key) {
+            Some(data) => data,
+            None => {
+                return Err(PyErr::new::<ImportError, _>(
+                    py,
+                    format!("cannot find extension module in memory: {}", key),
+                ));
+            }
+        };
+
+        let state = get_module_state(py, self.importer_module(py))?;
+
+        let path = materialize_extension_module(state, key, data).map_err(|e| {
+            PyErr::new::<RuntimeError, _>(
+                py,
+                format!("failed to materialize extension module {}: {}", key, e),
+            )
+        })?;
+
+        Ok(path.to_string_lossy().into_owned())
+    }
+
+    /// Materialize an in-memory extension module and wrap it in an `ExtensionFileLoader`.
+    fn extension_loader_for(
+        &self,
+        py: Python,
+        fullname: &PyObject,
+        key: &str,
+    ) -> PyResult<PyObject> {
+        let path = self.materialize_extension(py, key)?;
+
+        self.extension_file_loader(py)
+            .call(py, (fullname, path), None)
+    }
+
+    /// Obtain a memoryview for a module from `py_modules`, consulting and populating
+    /// `ModuleState::py_decompression_cache` for compressed entries.
+    ///
+    /// This uses a cache separate from `get_pyc_module_memory_view()`'s so a module
+    /// shipped with both source and bytecode doesn't have one overwrite the other's
+    /// cache slot.
+    fn get_py_module_memory_view(&self, py: Python, name: &str) -> PyResult<Option<PyObject>> {
+        let state = get_module_state(py, self.importer_module(py))?;
+
+        self.py_modules(py)
+            .get_memory_view(py, &mut state.py_decompression_cache, name)
+    }
+
+    /// Obtain a memoryview for a module from `pyc_modules`, consulting and populating
+    /// `ModuleState::pyc_decompression_cache` for compressed entries.
+    ///
+    /// See `get_py_module_memory_view()` for why this uses its own cache.
+    fn get_pyc_module_memory_view(&self, py: Python, name: &str) -> PyResult<Option<PyObject>> {
+        let state = get_module_state(py, self.importer_module(py))?;
+
+        self.pyc_modules(py)
+            .get_memory_view(py, &mut state.pyc_decompression_cache, name)
+    }
+}
+
 fn populate_packages(packages: &mut HashSet<&'static str>, name: &'static str) {
     let mut search = name;
 
@@ -257,8 +885,110 @@ fn populate_packages(packages: &mut HashSet<&'static str>, name: &'static str) {
     }
 }
 
+/// Construct a synthetic, deterministic filesystem path for an in-memory module.
+///
+/// The path never exists on disk: it only gives code relying on `__file__`
+/// (e.g. `os.path.dirname(__file__)`) something plausible to work with.
+fn synthetic_module_path(fullname: &str, is_package: bool) -> String {
+    let relative = fullname.replace('.', "/");
+
+    if is_package {
+        format!("<pyoxidizer>/{}/__init__.py", relative)
+    } else {
+        format!("<pyoxidizer>/{}.py", relative)
+    }
+}
+
+/// Obtain (creating if necessary) this instance's directory for extracting
+/// embedded extension modules into.
+///
+/// The directory is private to `state` (and thus to the owning importer
+/// module instance) so two `PyOxidizerFinder`/importer instances in the same
+/// process -- e.g. two embedded sub-interpreters with different extension
+/// blobs -- never share one, and so never collide on identical filenames.
+///
+/// Uses `tempfile::Builder` rather than a PID-derived path: it picks an
+/// unpredictable name and creates the directory atomically with owner-only
+/// permissions, failing outright if anything already occupies the chosen
+/// path. A PID-derived path is predictable, and `create_dir_all()` happily
+/// walks into a path that already exists as something else (e.g. a
+/// pre-staged symlink) -- letting a local attacker redirect what ends up
+/// getting `dlopen()`-ed (CWE-377).
+fn extension_module_temp_dir(state: &mut ModuleState) -> std::io::Result<&Path> {
+    if state.extension_module_temp_dir.is_none() {
+        let dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-extensions-")
+            .tempdir()?
+            .into_path();
+
+        state.extension_module_temp_dir = Some(dir);
+    }
+
+    Ok(state.extension_module_temp_dir.as_ref().unwrap())
+}
+
+/// Materialize an in-memory compiled extension module to a temp file on disk.
+///
+/// Dynamic linkers need a real file to `dlopen()`/`LoadLibrary()`, so the raw
+/// shared library bytes embedded in the binary are written out on first use.
+/// The platform-appropriate suffix is used so the result looks like a normal
+/// extension module to `importlib.machinery.ExtensionFileLoader`.
+fn materialize_extension_module(
+    state: &mut ModuleState,
+    name: &str,
+    data: &[u8],
+) -> std::io::Result<PathBuf> {
+    let suffix = if cfg!(windows) { "pyd" } else { "so" };
+    let filename = format!("{}.{}", name, suffix);
+    let path = extension_module_temp_dir(state)?.join(filename);
+
+    // `create_new` opens with `O_EXCL`-equivalent semantics: it fails rather
+    // than following a pre-existing symlink or silently truncating a file an
+    // attacker raced into place. Callers (`find_spec()`, then later
+    // `create_module()`/`exec_module()`) may materialize the same module more
+    // than once; since the filename is derived solely from `name` and this
+    // directory is privately ours, `AlreadyExists` just means a prior call
+    // already wrote the (identical) bytes, so it's safe to treat as success.
+    match std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+    {
+        Ok(mut file) => file.write_all(data)?,
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(e),
+    }
+
+    Ok(path)
+}
+
 const DOC: &[u8] = b"Binary representation of Python modules\0";
 
+/// The payload of a module supplied at runtime via `InitModuleState::additional_modules`.
+#[derive(Clone, Debug)]
+pub enum ModuleProviderData {
+    /// Python source code.
+    Source(Vec<u8>),
+    /// Compiled bytecode, as produced by `marshal.dumps(compile(...))`.
+    Bytecode(Vec<u8>),
+}
+
+/// A module supplied by an embedder at runtime.
+///
+/// This allows embedders to contribute modules beyond what was baked into
+/// `py_data`/`pyc_data` at build time -- e.g. plugins discovered at startup.
+#[derive(Clone, Debug)]
+pub struct ModuleProvider {
+    /// The fully qualified module name.
+    pub name: String,
+
+    /// The module's source or bytecode.
+    pub data: ModuleProviderData,
+
+    /// Whether this module is a package (has `__path__`).
+    pub is_package: bool,
+}
+
 /// Represents global module state to be passed at interpreter initialization time.
 #[derive(Debug)]
 pub struct InitModuleState {
@@ -267,6 +997,32 @@ pub struct InitModuleState {
 
     /// Raw data constituting Python module bytecode.
     pub pyc_data: &'static [u8],
+
+    /// Raw data constituting package resource (non-module) data.
+    pub resources_data: &'static [u8],
+
+    /// Raw data constituting distribution (`*.dist-info`) metadata files.
+    pub distributions_data: &'static [u8],
+
+    /// Raw data constituting compiled extension module (`.so`/`.pyd`) shared libraries.
+    pub extension_modules_data: &'static [u8],
+
+    /// Whether to set `origin`/`has_location` on specs for in-memory modules.
+    ///
+    /// When true, `__file__` is populated with a synthetic, non-existent
+    /// path. A package's `__path__` is an empty list whether or not this is
+    /// set. Embedders who don't want
+    /// `__file__` to exist at all -- because a non-existent path can
+    /// change `pkgutil`/`inspect` behavior -- can disable this.
+    pub set_module_origin: bool,
+
+    /// Additional modules supplied by the embedder at runtime.
+    ///
+    /// These are merged into `known_modules`/the in-memory module tables
+    /// after `py_data`/`pyc_data` are parsed, with "last write wins"
+    /// semantics: an entry here can override a same-named module baked
+    /// into those static blobs.
+    pub additional_modules: Vec<ModuleProvider>,
 }
 
 /// Holds reference to next module state struct.
@@ -281,6 +1037,7 @@ enum KnownModuleFlavor {
     Builtin,
     Frozen,
     InMemory,
+    InMemoryExtension,
 }
 
 type KnownModules = HashMap<&'static str, KnownModuleFlavor>;
@@ -298,6 +1055,37 @@ struct ModuleState {
     /// Raw data constituting Python module bytecode.
     pyc_data: &'static [u8],
 
+    /// Raw data constituting package resource (non-module) data.
+    resources_data: &'static [u8],
+
+    /// Raw data constituting distribution (`*.dist-info`) metadata files.
+    distributions_data: &'static [u8],
+
+    /// Raw data constituting compiled extension module (`.so`/`.pyd`) shared libraries.
+    extension_modules_data: &'static [u8],
+
+    /// Whether to set `origin`/`has_location` on specs for in-memory modules.
+    set_module_origin: bool,
+
+    /// Lazily-populated cache of inflated bytes for compressed modules in
+    /// `py_data`, keyed by module name.
+    ///
+    /// Kept separate from `pyc_decompression_cache` because a module can be
+    /// compressed under the same name in both `py_data` and `pyc_data` (source
+    /// and bytecode shipped side by side), and the two are not interchangeable.
+    py_decompression_cache: HashMap<&'static str, Vec<u8>>,
+
+    /// Lazily-populated cache of inflated bytes for compressed modules in
+    /// `pyc_data`, keyed by module name. See `py_decompression_cache`.
+    pyc_decompression_cache: HashMap<&'static str, Vec<u8>>,
+
+    /// Additional modules supplied by the embedder at runtime.
+    additional_modules: Vec<ModuleProvider>,
+
+    /// This instance's private directory for extracting embedded extension
+    /// modules into, lazily created by `extension_module_temp_dir()`.
+    extension_module_temp_dir: Option<PathBuf>,
+
     /// Whether setup() has been called.
     setup_called: bool,
 }
@@ -348,8 +1136,33 @@ fn module_init(py: Python, m: &PyModule) -> PyResult<()> {
     unsafe {
         state.py_data = (*NEXT_MODULE_STATE).py_data;
         state.pyc_data = (*NEXT_MODULE_STATE).pyc_data;
+        state.resources_data = (*NEXT_MODULE_STATE).resources_data;
+        state.distributions_data = (*NEXT_MODULE_STATE).distributions_data;
+        state.extension_modules_data = (*NEXT_MODULE_STATE).extension_modules_data;
+        state.set_module_origin = (*NEXT_MODULE_STATE).set_module_origin;
+
+        // `state` points at memory zeroed out by `PyModule_Create`, not a
+        // properly constructed `ModuleState`. `*_decompression_cache`,
+        // `additional_modules`, and `extension_module_temp_dir` have Drop
+        // glue, so writing to them with `=` would first drop a bogus value
+        // built from that zeroed memory. Use `ptr::write()` to initialize
+        // them in place without dropping the old (garbage) value.
+        std::ptr::write(&mut state.py_decompression_cache, HashMap::new());
+        std::ptr::write(&mut state.pyc_decompression_cache, HashMap::new());
+        std::ptr::write(
+            &mut state.additional_modules,
+            (*NEXT_MODULE_STATE).additional_modules.clone(),
+        );
+        std::ptr::write(&mut state.extension_module_temp_dir, None);
     }
 
+    // `state` (and the `'static` views `get_memory_view()`/`additional_modules`
+    // hand out into its caches) is only really `'static` if this module object is
+    // never deallocated. Nothing otherwise pins a strong reference to it -- it's
+    // never stashed in `sys.modules` by this file -- so leak one here to turn
+    // that assumption into an enforced invariant.
+    std::mem::forget(m.clone_ref(py));
+
     state.setup_called = false;
 
     m.add(
@@ -417,16 +1230,57 @@ fn module_setup(
     let builtin_importer = meta_path.get_item(py, 0);
     let frozen_importer = meta_path.get_item(py, 1);
 
-    let py_modules = match parse_modules_blob(state.py_data) {
+    let (mut py_modules, py_modules_packages) = match parse_versioned_modules_blob(state.py_data) {
         Ok(value) => value,
         Err(msg) => return Err(PyErr::new::<ValueError, _>(py, msg)),
     };
 
-    let pyc_modules = match parse_modules_blob(state.pyc_data) {
+    let (mut pyc_modules, pyc_modules_packages) = match parse_versioned_modules_blob(state.pyc_data)
+    {
         Ok(value) => value,
         Err(msg) => return Err(PyErr::new::<ValueError, _>(py, msg)),
     };
 
+    let resources = match parse_resources_blob(state.resources_data) {
+        Ok(value) => value,
+        Err(msg) => return Err(PyErr::new::<ValueError, _>(py, msg)),
+    };
+
+    let distributions = match parse_resources_blob(state.distributions_data) {
+        Ok(value) => value,
+        Err(msg) => return Err(PyErr::new::<ValueError, _>(py, msg)),
+    };
+
+    let extension_modules = match parse_modules_blob(state.extension_modules_data) {
+        Ok(value) => value,
+        Err(msg) => return Err(PyErr::new::<ValueError, _>(py, msg)),
+    };
+
+    let extension_file_loader = py
+        .import("importlib.machinery")?
+        .get(py, "ExtensionFileLoader")?;
+
+    let importlib_metadata = py.import("importlib.metadata")?;
+    let distribution_base = importlib_metadata.get(py, "Distribution")?;
+
+    let distribution_class_globals = PyDict::new(py);
+    py.run(
+        DISTRIBUTION_CLASS_SOURCE,
+        Some(&distribution_class_globals),
+        None,
+    )?;
+    let make_distribution_class =
+        match distribution_class_globals.get_item(py, "_pyoxidizer_make_distribution_class") {
+            Some(v) => v,
+            None => {
+                return Err(PyErr::new::<ValueError, _>(
+                    py,
+                    "could not find distribution class factory",
+                ));
+            }
+        };
+    let distribution_class = make_distribution_class.call(py, (distribution_base,), None)?;
+
     // Populate our known module lookup table with entries from builtins, frozens, and
     // finally us. Last write wins and has the same effect as registering our
     // meta path importer first. This should be safe. If nothing else, it allows
@@ -475,22 +1329,70 @@ fn module_setup(
         known_modules.insert(name_str, KnownModuleFlavor::Frozen);
     }
 
-    // TODO consider baking set of packages into embedded data.
-    let mut packages: HashSet<&'static str> = HashSet::with_capacity(pyc_modules.len());
+    // The package set is baked into v2 modules blobs (see `parse_modules_blob_v2`);
+    // v1 blobs fall back to deriving it from dotted module names at parse time.
+    let mut packages: HashSet<&'static str> =
+        HashSet::with_capacity(py_modules_packages.len() + pyc_modules_packages.len());
+    packages.extend(py_modules_packages);
+    packages.extend(pyc_modules_packages);
+
+    // Merge in modules the embedder registered at runtime. These are applied
+    // after the static blobs are parsed, so a provider here takes precedence
+    // over a same-named module from `py_data`/`pyc_data` -- the same "last
+    // write wins" semantics used for builtins/frozen/in-memory above.
+    for provider in &state.additional_modules {
+        // SAFETY: `provider`'s owned buffers live inside `state.additional_modules`,
+        // which in turn lives inside the `ModuleState` owned by the importer
+        // module object -- `module_init()` leaks a strong reference to that
+        // object so it's never deallocated, making borrows of them effectively
+        // `'static`.
+        let name: &'static str = unsafe { std::mem::transmute(provider.name.as_str()) };
+
+        populate_packages(&mut packages, name);
+        if provider.is_package {
+            packages.insert(name);
+        }
+
+        let entry = |data: &'static [u8]| ModuleBlobEntry {
+            data,
+            compressed: false,
+        };
+
+        match &provider.data {
+            ModuleProviderData::Source(data) => {
+                let data: &'static [u8] = unsafe { std::mem::transmute(data.as_slice()) };
+                py_modules.insert(name, entry(data));
+            }
+            ModuleProviderData::Bytecode(data) => {
+                let data: &'static [u8] = unsafe { std::mem::transmute(data.as_slice()) };
+                pyc_modules.insert(name, entry(data));
+            }
+        }
+    }
 
     for key in py_modules.keys() {
         known_modules.insert(key, KnownModuleFlavor::InMemory);
-        populate_packages(&mut packages, key);
     }
 
     for key in pyc_modules.keys() {
         known_modules.insert(key, KnownModuleFlavor::InMemory);
+    }
+
+    for key in extension_modules.keys() {
+        known_modules.insert(key, KnownModuleFlavor::InMemoryExtension);
         populate_packages(&mut packages, key);
     }
 
     let marshal_loads = marshal_module.get(py, "loads")?;
     let call_with_frames_removed = bootstrap_module.get(py, "_call_with_frames_removed")?;
     let module_spec_type = bootstrap_module.get(py, "ModuleSpec")?;
+    let sys_audit = sys_module.get(py, "audit")?;
+    let sys_path = sys_module.get(py, "path")?;
+    // A second, independent handle on the same `sys.meta_path` list `meta_path_object`
+    // already refers to -- both point at the live list, so appending ourselves to it
+    // below is reflected here too.
+    let sys_meta_path = sys_module.get(py, "meta_path")?;
+    let sys_path_hooks = sys_module.get(py, "path_hooks")?;
 
     let builtins_module =
         match unsafe { PyObject::from_borrowed_ptr_opt(py, pyffi::PyEval_GetBuiltins()) } {
@@ -516,6 +1418,7 @@ fn module_setup(
     let unified_importer = PyOxidizerFinder::create_instance(
         py,
         imp_module,
+        m,
         marshal_loads,
         builtin_importer,
         frozen_importer,
@@ -523,10 +1426,28 @@ fn module_setup(
         module_spec_type,
         decode_source,
         exec_fn,
-        PythonModulesData { data: py_modules },
-        PythonModulesData { data: pyc_modules },
+        sys_audit,
+        sys_path,
+        sys_meta_path,
+        sys_path_hooks,
+        PythonModulesBlob {
+            entries: py_modules,
+        },
+        PythonModulesBlob {
+            entries: pyc_modules,
+        },
+        PythonModulesData {
+            data: extension_modules,
+        },
+        extension_file_loader,
+        PythonPackageResources { data: resources },
+        PythonDistributionsData {
+            data: distributions,
+        },
+        distribution_class,
         packages,
         known_modules,
+        state.set_module_origin,
     )?;
     meta_path_object.call_method(py, "clear", NoArgs, None)?;
     meta_path_object.call_method(py, "append", (unified_importer,), None)?;
@@ -566,3 +1487,114 @@ pub extern "C" fn PyInit__pyoxidizer_importer() -> *mut pyffi::PyObject {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Leak a byte vector to get a `&'static [u8]`, matching the lifetime the
+    /// real blob parsers are handed (a slice into the binary's own data section).
+    fn leak(data: Vec<u8>) -> &'static [u8] {
+        Box::leak(data.into_boxed_slice())
+    }
+
+    #[test]
+    fn parse_modules_blob_v1_roundtrip() {
+        let entries: &[(&str, &[u8])] = &[("foo", b"foo source"), ("foo.bar", b"bar source")];
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+        for (name, value) in entries {
+            data.extend_from_slice(&(name.len() as u32).to_le_bytes());
+            data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        }
+        for (name, _) in entries {
+            data.extend_from_slice(name.as_bytes());
+        }
+        for (_, value) in entries {
+            data.extend_from_slice(value);
+        }
+
+        let (parsed, packages) = parse_modules_blob_v1(leak(data)).unwrap();
+
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed["foo"].data, b"foo source");
+        assert!(!parsed["foo"].compressed);
+        assert_eq!(parsed["foo.bar"].data, b"bar source");
+        assert!(packages.contains("foo"));
+    }
+
+    #[test]
+    fn parse_modules_blob_v2_roundtrip() {
+        let entries: &[(&str, &[u8], u8)] = &[
+            ("foo", b"foo source", 0),
+            ("foo.bar", b"bar source", MODULE_FLAG_PACKAGE),
+            ("baz", b"compressed bytes", MODULE_FLAG_COMPRESSED),
+        ];
+
+        let mut data = Vec::new();
+        data.extend_from_slice(MODULES_BLOB_MAGIC);
+        data.push(MODULES_BLOB_VERSION);
+        data.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+        for (name, value, flags) in entries {
+            data.extend_from_slice(&(name.len() as u32).to_le_bytes());
+            data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            data.push(*flags);
+        }
+        for (name, _, _) in entries {
+            data.extend_from_slice(name.as_bytes());
+        }
+        for (_, value, _) in entries {
+            data.extend_from_slice(value);
+        }
+
+        let (parsed, packages) = parse_modules_blob_v2(leak(data)).unwrap();
+
+        assert_eq!(parsed.len(), 3);
+        assert_eq!(parsed["foo"].data, b"foo source");
+        assert!(!parsed["foo"].compressed);
+        assert_eq!(parsed["baz"].data, b"compressed bytes");
+        assert!(parsed["baz"].compressed);
+        assert!(packages.contains("foo.bar"));
+        assert!(!packages.contains("foo"));
+    }
+
+    #[test]
+    fn parse_modules_blob_v2_rejects_bad_version() {
+        let mut data = Vec::new();
+        data.extend_from_slice(MODULES_BLOB_MAGIC);
+        data.push(MODULES_BLOB_VERSION + 1);
+        data.extend_from_slice(&0u32.to_le_bytes());
+
+        assert!(parse_modules_blob_v2(leak(data)).is_err());
+    }
+
+    #[test]
+    fn parse_resources_blob_roundtrip() {
+        let entries: &[(&str, &str, &[u8])] = &[
+            ("pkg", "data.txt", b"hello"),
+            ("pkg.sub", "other.bin", b"world!!"),
+        ];
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+        for (package, name, value) in entries {
+            data.extend_from_slice(&(package.len() as u32).to_le_bytes());
+            data.extend_from_slice(&(name.len() as u32).to_le_bytes());
+            data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        }
+        for (package, name, _) in entries {
+            data.extend_from_slice(package.as_bytes());
+            data.extend_from_slice(name.as_bytes());
+        }
+        for (_, _, value) in entries {
+            data.extend_from_slice(value);
+        }
+
+        let parsed = parse_resources_blob(leak(data)).unwrap();
+
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[&("pkg", "data.txt")], b"hello");
+        assert_eq!(parsed[&("pkg.sub", "other.bin")], b"world!!");
+    }
+}